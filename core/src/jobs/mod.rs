@@ -0,0 +1,12 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Persisted progress for long-running background jobs (e.g. face scanning) so they can
+//! pause cleanly on shutdown and resume on next launch instead of starting over.
+
+pub mod model;
+pub mod repo;
+
+pub use model::{FaceScanCursor, Job, JobKind, JobStatus};
+pub use repo::Repository;
@@ -0,0 +1,98 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use serde::{Deserialize, Serialize};
+
+/// Identifies which background job a [Job] row tracks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    FaceScan,
+}
+
+impl JobKind {
+    /// The `jobs.kind` column value for this kind. `pub(crate)` rather than `pub(super)`
+    /// because callers that checkpoint job progress in the same transaction as their own
+    /// domain tables (e.g. `people::Repository`) need to build the `jobs` upsert by hand to
+    /// avoid taking the jobs connection lock while already holding their own.
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            JobKind::FaceScan => "face_scan",
+        }
+    }
+
+    pub(super) fn from_str(s: &str) -> Option<JobKind> {
+        match s {
+            "face_scan" => Some(JobKind::FaceScan),
+            _ => None,
+        }
+    }
+}
+
+/// Lifecycle of a background job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Paused,
+    Done,
+    Failed,
+}
+
+impl JobStatus {
+    /// See the note on [JobKind::as_str] for why this is `pub(crate)`.
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Paused => "paused",
+            JobStatus::Done => "done",
+            JobStatus::Failed => "failed",
+        }
+    }
+
+    pub(super) fn from_str(s: &str) -> Option<JobStatus> {
+        match s {
+            "queued" => Some(JobStatus::Queued),
+            "running" => Some(JobStatus::Running),
+            "paused" => Some(JobStatus::Paused),
+            "done" => Some(JobStatus::Done),
+            "failed" => Some(JobStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// A persisted background job, loaded from the `jobs` table.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub kind: JobKind,
+    pub status: JobStatus,
+
+    /// Opaque MessagePack-encoded progress state, understood only by the job's own worker.
+    pub state: Vec<u8>,
+
+    pub processed_count: i64,
+    pub total_count: i64,
+}
+
+/// Resumption cursor for the face scan job.
+///
+/// Serialized into [Job::state] with `rmp-serde` after each processed batch. `pictures` are
+/// scanned ordered by `ordering_ts DESC`, so resuming means skipping everything at-or-after
+/// this position.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaceScanCursor {
+    pub last_ordering_ts: String,
+    pub last_picture_id: i64,
+}
+
+impl FaceScanCursor {
+    pub fn encode(&self) -> rmp_serde::encode::Result<Vec<u8>> {
+        rmp_serde::to_vec(self)
+    }
+
+    pub fn decode(bytes: &[u8]) -> rmp_serde::decode::Result<FaceScanCursor> {
+        rmp_serde::from_slice(bytes)
+    }
+}
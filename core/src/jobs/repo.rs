@@ -0,0 +1,132 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::jobs::model::{Job, JobKind, JobStatus};
+use anyhow::*;
+use rusqlite;
+use rusqlite::params;
+use rusqlite::OptionalExtension;
+use rusqlite::Row;
+use std::sync::{Arc, Mutex};
+
+/// Repository of background job progress.
+///
+/// Backed by a `jobs` table keyed on job kind, storing a status enum and an opaque
+/// MessagePack state blob so a long-running job (e.g. face scanning) can checkpoint its
+/// progress and resume from the same place after a crash or a graceful quit.
+#[derive(Debug, Clone)]
+pub struct Repository {
+    /// Connection to backing Sqlite database.
+    con: Arc<Mutex<rusqlite::Connection>>,
+}
+
+impl Repository {
+    /// Builds a Repository. The `jobs` table itself is created by the application's schema
+    /// migrations.
+    pub fn open(con: Arc<Mutex<rusqlite::Connection>>) -> Result<Repository> {
+        Ok(Repository { con })
+    }
+
+    /// Loads a job's persisted progress, if any exists, regardless of status.
+    pub fn find_job(&self, kind: JobKind) -> Result<Option<Job>> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare_cached(
+            "SELECT status, state, processed_count, total_count
+             FROM jobs
+             WHERE kind = ?1",
+        )?;
+
+        let job = stmt
+            .query_row(params![kind.as_str()], |row| self.to_job(kind, row))
+            .optional()?;
+
+        Ok(job)
+    }
+
+    /// Loads a job only if it is `running` or `paused`, i.e. it was left mid-flight and
+    /// should be continued on startup rather than started fresh.
+    pub fn find_resumable_job(&self, kind: JobKind) -> Result<Option<Job>> {
+        let job = self.find_job(kind)?;
+        Ok(job.filter(|j| matches!(j.status, JobStatus::Running | JobStatus::Paused)))
+    }
+
+    /// Checkpoints a job's progress. Call this after each processed batch so a crash or
+    /// quit loses at most one batch of work.
+    pub fn checkpoint(
+        &self,
+        kind: JobKind,
+        status: JobStatus,
+        state: &[u8],
+        processed_count: i64,
+        total_count: i64,
+    ) -> Result<()> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare_cached(
+            "INSERT INTO jobs (
+                kind,
+                status,
+                state,
+                processed_count,
+                total_count,
+                updated_ts
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, CURRENT_TIMESTAMP
+            ) ON CONFLICT (kind) DO UPDATE SET
+                status = ?2,
+                state = ?3,
+                processed_count = ?4,
+                total_count = ?5,
+                updated_ts = CURRENT_TIMESTAMP",
+        )?;
+
+        stmt.execute(params![
+            kind.as_str(),
+            status.as_str(),
+            state,
+            processed_count,
+            total_count,
+        ])?;
+
+        Ok(())
+    }
+
+    /// Marks a job `paused` without touching its last-checkpointed state, so it resumes
+    /// from the same cursor on next launch. Call this on a graceful exit signal.
+    pub fn mark_paused(&self, kind: JobKind) -> Result<()> {
+        self.set_status(kind, JobStatus::Paused)
+    }
+
+    pub fn mark_done(&self, kind: JobKind) -> Result<()> {
+        self.set_status(kind, JobStatus::Done)
+    }
+
+    pub fn mark_failed(&self, kind: JobKind) -> Result<()> {
+        self.set_status(kind, JobStatus::Failed)
+    }
+
+    fn set_status(&self, kind: JobKind, status: JobStatus) -> Result<()> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare_cached(
+            "UPDATE jobs SET status = ?2, updated_ts = CURRENT_TIMESTAMP WHERE kind = ?1",
+        )?;
+        stmt.execute(params![kind.as_str(), status.as_str()])?;
+        Ok(())
+    }
+
+    fn to_job(&self, kind: JobKind, row: &Row<'_>) -> rusqlite::Result<Job> {
+        let status: String = row.get("status")?;
+        let status = JobStatus::from_str(&status).unwrap_or(JobStatus::Queued);
+        let state: Vec<u8> = row.get("state")?;
+        let processed_count = row.get("processed_count")?;
+        let total_count = row.get("total_count")?;
+
+        std::result::Result::Ok(Job {
+            kind,
+            status,
+            state,
+            processed_count,
+            total_count,
+        })
+    }
+}
@@ -12,9 +12,58 @@ use crate::people::PersonId;
 use anyhow::*;
 use rusqlite;
 use rusqlite::params;
+use rusqlite::OptionalExtension;
 use rusqlite::Row;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Work item for the thumbnail-generation worker: everything needed to crop a face's
+/// thumbnail and bounds images out of its source picture, independent of whether detection
+/// has finished writing those crops yet.
+#[derive(Debug, Clone)]
+pub struct FaceThumbnailJob {
+    pub face_id: FaceId,
+    pub picture_path: PathBuf,
+    pub thumbnail_path: PathBuf,
+    pub bounds_path: PathBuf,
+    pub bounds_x: f64,
+    pub bounds_y: f64,
+    pub bounds_width: f64,
+    pub bounds_height: f64,
+}
+
+/// A reversible mutation to people/face data, recorded in the `operation_log` table so it
+/// can be undone and redone across restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Operation {
+    MarkedNotAFace {
+        face_id: FaceId,
+        prior_is_face: bool,
+    },
+    AssignedPerson {
+        face_id: FaceId,
+        prior_person_id: Option<PersonId>,
+        prior_is_face: bool,
+        new_person_id: PersonId,
+    },
+    CreatedPerson {
+        person_id: PersonId,
+        name: String,
+        thumbnail_path: String,
+    },
+}
+
+impl Operation {
+    fn encode(&self) -> rmp_serde::encode::Result<Vec<u8>> {
+        rmp_serde::to_vec(self)
+    }
+
+    fn decode(bytes: &[u8]) -> rmp_serde::decode::Result<Operation> {
+        rmp_serde::from_slice(bytes)
+    }
+}
 
 /// Repository of people data.
 /// Repository is backed by a Sqlite database.
@@ -55,7 +104,10 @@ impl Repository {
 
     /// FIXME should all the *face* functions move to a new repository?
     /// Gets all pictures that haven't been inspected for containing a motion photo.
-    pub fn find_need_face_scan(&self) -> Result<Vec<(PictureId, PathBuf)>> {
+    ///
+    /// For a scan that should survive being interrupted, prefer
+    /// [Self::find_need_face_scan_after] with a cursor checkpointed in the `jobs` table.
+    pub fn find_need_face_scan(&self) -> Result<Vec<(PictureId, PathBuf, String)>> {
         let con = self.con.lock().unwrap();
         let mut stmt = con.prepare(
             "SELECT
@@ -72,11 +124,67 @@ impl Repository {
                 LEFT OUTER JOIN pictures_face_scans USING (picture_id)
                 WHERE pictures_face_scans.picture_id IS NULL
                 AND COALESCE(pictures.is_broken, FALSE) IS FALSE
-                ORDER BY ordering_ts DESC",
+                ORDER BY ordering_ts DESC, pictures.picture_id DESC",
         )?;
 
         let result = stmt
-            .query_map([], |row| self.to_picture_id_path_tuple(row))?
+            .query_map([], |row| self.to_picture_id_path_ts_tuple(row))?
+            .flatten()
+            .collect();
+
+        Ok(result)
+    }
+
+    /// Like [Self::find_need_face_scan], but skips everything at-or-after `cursor` so a face
+    /// scan job that was paused or interrupted resumes instead of restarting from the newest
+    /// picture.
+    pub fn find_need_face_scan_after(
+        &self,
+        cursor: &crate::jobs::FaceScanCursor,
+    ) -> Result<Vec<(PictureId, PathBuf, String)>> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare(
+            "SELECT
+                    pictures.picture_id,
+                    pictures.picture_path_b64,
+                    COALESCE(
+                        pictures.exif_created_ts,
+                        pictures.exif_modified_ts,
+                        pictures.fs_created_ts,
+                        pictures.fs_modified_ts,
+                        CURRENT_TIMESTAMP
+                    ) AS ordering_ts
+                FROM pictures
+                LEFT OUTER JOIN pictures_face_scans USING (picture_id)
+                WHERE pictures_face_scans.picture_id IS NULL
+                AND COALESCE(pictures.is_broken, FALSE) IS FALSE
+                AND (
+                    COALESCE(
+                        pictures.exif_created_ts,
+                        pictures.exif_modified_ts,
+                        pictures.fs_created_ts,
+                        pictures.fs_modified_ts,
+                        CURRENT_TIMESTAMP
+                    ) < ?1
+                    OR (
+                        COALESCE(
+                            pictures.exif_created_ts,
+                            pictures.exif_modified_ts,
+                            pictures.fs_created_ts,
+                            pictures.fs_modified_ts,
+                            CURRENT_TIMESTAMP
+                        ) = ?1
+                        AND pictures.picture_id < ?2
+                    )
+                )
+                ORDER BY ordering_ts DESC, pictures.picture_id DESC",
+        )?;
+
+        let result = stmt
+            .query_map(
+                params![cursor.last_ordering_ts, cursor.last_picture_id],
+                |row| self.to_picture_id_path_ts_tuple(row),
+            )?
             .flatten()
             .collect();
 
@@ -112,29 +220,87 @@ impl Repository {
         let mut con = self.con.lock().unwrap();
         let tx = con.transaction()?;
 
-        {
-            let mut stmt = tx.prepare_cached(
-                "INSERT INTO pictures_face_scans (
-                    picture_id,
-                    is_broken,
-                    face_count,
-                    scan_ts
-                ) VALUES (
-                    ?1, TRUE, 0, CURRENT_TIMESTAMP
-                ) ON CONFLICT (picture_id) DO UPDATE SET
-                    is_broken = true,
-                    face_count = 0,
-                    scan_ts = CURRENT_TIMESTAMP
-                ",
-            )?;
+        Self::insert_face_scan_broken_row(&tx, picture_id)?;
 
-            stmt.execute(params![picture_id.id(),])?;
-        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Like [Self::mark_face_scan_broken], but also checkpoints face-scan job progress into
+    /// the `jobs` table in the same transaction, so a crash or quit mid-batch loses at most
+    /// one picture's worth of scanning work. Intended to be called once per broken picture
+    /// from the face scan loop in place of [Self::mark_face_scan_broken], mirroring
+    /// [Self::add_face_scans_checkpointed] for the non-broken case.
+    pub fn mark_face_scan_broken_checkpointed(
+        &mut self,
+        picture_id: &PictureId,
+        cursor: &crate::jobs::FaceScanCursor,
+        processed_count: i64,
+        total_count: i64,
+    ) -> Result<()> {
+        let mut con = self.con.lock().unwrap();
+        let tx = con.transaction()?;
+
+        Self::insert_face_scan_broken_row(&tx, picture_id)?;
+
+        let state = cursor.encode()?;
+        tx.prepare_cached(
+            "INSERT INTO jobs (
+                kind,
+                status,
+                state,
+                processed_count,
+                total_count,
+                updated_ts
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, CURRENT_TIMESTAMP
+            ) ON CONFLICT (kind) DO UPDATE SET
+                status = ?2,
+                state = ?3,
+                processed_count = ?4,
+                total_count = ?5,
+                updated_ts = CURRENT_TIMESTAMP",
+        )?
+        .execute(params![
+            crate::jobs::JobKind::FaceScan.as_str(),
+            crate::jobs::JobStatus::Running.as_str(),
+            state,
+            processed_count,
+            total_count,
+        ])?;
 
         tx.commit()?;
         Ok(())
     }
 
+    /// Shared by [Self::mark_face_scan_broken] and [Self::mark_face_scan_broken_checkpointed]:
+    /// writes the `pictures_face_scans` row marking `picture_id` as broken.
+    fn insert_face_scan_broken_row(tx: &rusqlite::Transaction, picture_id: &PictureId) -> Result<()> {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO pictures_face_scans (
+                picture_id,
+                is_broken,
+                face_count,
+                scan_ts
+            ) VALUES (
+                ?1, TRUE, 0, CURRENT_TIMESTAMP
+            ) ON CONFLICT (picture_id) DO UPDATE SET
+                is_broken = true,
+                face_count = 0,
+                scan_ts = CURRENT_TIMESTAMP
+            ",
+        )?;
+
+        stmt.execute(params![picture_id.id(),])?;
+        Ok(())
+    }
+
+    /// Records detected face geometry for `picture_id`. The thumbnail/bounds crop files named
+    /// by `face.thumbnail_path`/`face.bounds_path` don't need to exist yet: each row is
+    /// inserted with `thumbnail_generated = FALSE`, and a separate worker renders the crops
+    /// later via [Self::find_faces_needing_thumbnails] and [Self::mark_thumbnail_generated].
+    /// This lets detection finish and populate the database quickly while the heavier image
+    /// I/O proceeds independently.
     pub fn add_face_scans(
         &mut self,
         picture_id: &PictureId,
@@ -143,110 +309,331 @@ impl Repository {
         let mut con = self.con.lock().unwrap();
         let tx = con.transaction()?;
 
-        // Create a scope to make borrowing of tx not be an error.
+        Self::insert_face_scan_rows(&tx, &self.cache_dir_base_path, picture_id, faces)?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Like [Self::add_face_scans], but also checkpoints face-scan job progress into the
+    /// `jobs` table in the same transaction, so a crash or quit mid-batch loses at most one
+    /// picture's worth of scanning work. Intended to be called once per picture from the
+    /// face scan loop in place of [Self::add_face_scans].
+    pub fn add_face_scans_checkpointed(
+        &mut self,
+        picture_id: &PictureId,
+        faces: &Vec<face_extractor::Face>,
+        cursor: &crate::jobs::FaceScanCursor,
+        processed_count: i64,
+        total_count: i64,
+    ) -> Result<()> {
+        let mut con = self.con.lock().unwrap();
+        let tx = con.transaction()?;
+
+        Self::insert_face_scan_rows(&tx, &self.cache_dir_base_path, picture_id, faces)?;
+
+        let state = cursor.encode()?;
+        tx.prepare_cached(
+            "INSERT INTO jobs (
+                kind,
+                status,
+                state,
+                processed_count,
+                total_count,
+                updated_ts
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, CURRENT_TIMESTAMP
+            ) ON CONFLICT (kind) DO UPDATE SET
+                status = ?2,
+                state = ?3,
+                processed_count = ?4,
+                total_count = ?5,
+                updated_ts = CURRENT_TIMESTAMP",
+        )?
+        .execute(params![
+            crate::jobs::JobKind::FaceScan.as_str(),
+            crate::jobs::JobStatus::Running.as_str(),
+            state,
+            processed_count,
+            total_count,
+        ])?;
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Shared by [Self::add_face_scans] and [Self::add_face_scans_checkpointed]: writes the
+    /// `pictures_face_scans` and `pictures_faces` rows for a picture's detected faces.
+    fn insert_face_scan_rows(
+        tx: &rusqlite::Transaction,
+        cache_dir_base_path: &Path,
+        picture_id: &PictureId,
+        faces: &Vec<face_extractor::Face>,
+    ) -> Result<()> {
+        let mut scan_insert_stmt = tx.prepare_cached(
+            "INSERT INTO pictures_face_scans (
+                picture_id,
+                is_broken,
+                face_count,
+                scan_ts
+            ) VALUES (
+                ?1, ?2, ?3, CURRENT_TIMESTAMP
+            ) ON CONFLICT (picture_id) DO UPDATE SET
+                is_broken = ?2,
+                face_count = ?3,
+                scan_ts = CURRENT_TIMESTAMP
+            ",
+        )?;
+
+        scan_insert_stmt.execute(params![picture_id.id(), false, faces.len(),])?;
+
+        let mut face_insert_stmt = tx.prepare_cached(
+            "INSERT INTO pictures_faces (
+                picture_id,
+                thumbnail_path,
+                bounds_path,
+
+                model_name,
+
+                bounds_x,
+                bounds_y,
+                bounds_width,
+                bounds_height,
+
+                right_eye_x,
+                right_eye_y,
+
+                left_eye_x,
+                left_eye_y,
+
+                nose_x,
+                nose_y,
+
+                right_mouth_corner_x,
+                right_mouth_corner_y,
+
+                left_mouth_corner_x,
+                left_mouth_corner_y,
+
+                confidence,
+
+                is_face,
+                thumbnail_generated
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
+                ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, true, FALSE
+            )
+            ",
+        )?;
+
+        for face in faces {
+            // convert to relative path before saving to database
+            let thumbnail_path = face.thumbnail_path.strip_prefix(cache_dir_base_path)?;
+            let bounds_path = face.bounds_path.strip_prefix(cache_dir_base_path)?;
+
+            let right_eye = face.right_eye();
+            let left_eye = face.left_eye();
+            let nose = face.nose();
+            let right_mouth_corner = face.right_mouth_corner();
+            let left_mouth_corner = face.left_mouth_corner();
+
+            face_insert_stmt.execute(params![
+                picture_id.id(),
+                thumbnail_path.to_string_lossy(),
+                bounds_path.to_string_lossy(),
+                face.model_name,
+                face.bounds.x,
+                face.bounds.y,
+                face.bounds.width,
+                face.bounds.height,
+                right_eye.map(|x| x.0),
+                right_eye.map(|x| x.1),
+                left_eye.map(|x| x.0),
+                left_eye.map(|x| x.1),
+                nose.map(|x| x.0),
+                nose.map(|x| x.1),
+                right_mouth_corner.map(|x| x.0),
+                right_mouth_corner.map(|x| x.1),
+                left_mouth_corner.map(|x| x.0),
+                left_mouth_corner.map(|x| x.1),
+                face.confidence
+            ])?;
+        }
+
+        Ok(())
+    }
+
+    /// Lists every known person, for populating a person picker.
+    pub fn all_people(&self) -> Result<Vec<model::Person>> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare(
+            "SELECT person_id, name, thumbnail_path FROM people ORDER BY name ASC",
+        )?;
+
+        let result = stmt
+            .query_map([], |row| self.to_person(row))?
+            .flatten()
+            .collect();
+
+        Ok(result)
+    }
+
+    /// Assigns a face to an existing person. Reversible via [Self::undo].
+    pub fn assign_face_to_person(&mut self, face_id: FaceId, person_id: PersonId) -> Result<()> {
+        let mut con = self.con.lock().unwrap();
+        let tx = con.transaction()?;
+
         {
-            let mut scan_insert_stmt = tx.prepare_cached(
-                "INSERT INTO pictures_face_scans (
-                    picture_id,
-                    is_broken,
-                    face_count,
-                    scan_ts
-                ) VALUES (
-                    ?1, ?2, ?3, CURRENT_TIMESTAMP
-                ) ON CONFLICT (picture_id) DO UPDATE SET
-                    is_broken = ?2,
-                    face_count = ?3,
-                    scan_ts = CURRENT_TIMESTAMP
-                ",
+            let (prior_person_id, prior_is_face): (Option<i64>, bool) = tx.query_row(
+                "SELECT person_id, is_face FROM pictures_faces WHERE face_id = ?1",
+                params![face_id.id()],
+                |row| std::result::Result::Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            let prior_person_id = prior_person_id.map(PersonId::new);
+
+            let mut stmt = tx.prepare_cached(
+                "UPDATE pictures_faces
+                SET person_id = ?2, is_face = TRUE
+                WHERE face_id = ?1",
+            )?;
+
+            stmt.execute(params![face_id.id(), person_id.id()])?;
+
+            Self::record_operation(
+                &tx,
+                &Operation::AssignedPerson {
+                    face_id,
+                    prior_person_id,
+                    prior_is_face,
+                    new_person_id: person_id,
+                },
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Creates a new person, using `thumbnail_from_face_id`'s existing crop as the person's
+    /// thumbnail. Reversible via [Self::undo].
+    pub fn add_person(&mut self, name: &str, thumbnail_from_face_id: FaceId) -> Result<PersonId> {
+        let mut con = self.con.lock().unwrap();
+        let tx = con.transaction()?;
+
+        let person_id = {
+            let thumbnail_path: String = tx.query_row(
+                "SELECT thumbnail_path FROM pictures_faces WHERE face_id = ?1",
+                params![thumbnail_from_face_id.id()],
+                |row| row.get(0),
             )?;
 
-            scan_insert_stmt.execute(params![picture_id.id(), false, faces.len(),])?;
+            let mut stmt = tx
+                .prepare_cached("INSERT INTO people (name, thumbnail_path) VALUES (?1, ?2)")?;
+            stmt.execute(params![name, thumbnail_path])?;
 
-            let mut face_insert_stmt = tx.prepare_cached(
-                "INSERT INTO pictures_faces (
-                    picture_id,
+            let person_id = PersonId::new(tx.last_insert_rowid());
+
+            Self::record_operation(
+                &tx,
+                &Operation::CreatedPerson {
+                    person_id,
+                    name: name.to_string(),
                     thumbnail_path,
-                    bounds_path,
+                },
+            )?;
+
+            person_id
+        };
+
+        tx.commit()?;
+        Ok(person_id)
+    }
 
-                    model_name,
+    /// Finds faces whose thumbnail/bounds crop files have not yet been rendered, so a
+    /// dedicated worker can generate them independently of detection. Because this is
+    /// driven off the persisted `thumbnail_generated` flag rather than an in-memory
+    /// position, a face dropped back into this queue by an app restart is simply found
+    /// again here instead of needing separate resumption state.
+    pub fn find_faces_needing_thumbnails(&self) -> Result<Vec<FaceThumbnailJob>> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare(
+            "SELECT
+                    pictures_faces.face_id AS face_id,
+                    pictures.picture_path_b64 AS picture_path_b64,
+                    pictures_faces.thumbnail_path AS thumbnail_path,
+                    pictures_faces.bounds_path AS bounds_path,
+                    pictures_faces.bounds_x AS bounds_x,
+                    pictures_faces.bounds_y AS bounds_y,
+                    pictures_faces.bounds_width AS bounds_width,
+                    pictures_faces.bounds_height AS bounds_height
+                FROM pictures_faces
+                JOIN pictures USING (picture_id)
+                WHERE COALESCE(pictures_faces.thumbnail_generated, FALSE) IS FALSE
+                ORDER BY pictures_faces.face_id ASC",
+        )?;
 
-                    bounds_x,
-                    bounds_y,
-                    bounds_width,
-                    bounds_height,
+        let result = stmt
+            .query_map([], |row| self.to_face_thumbnail_job(row))?
+            .flatten()
+            .collect();
 
-                    right_eye_x,
-                    right_eye_y,
+        Ok(result)
+    }
 
-                    left_eye_x,
-                    left_eye_y,
+    /// Marks a face's thumbnail/bounds crops as rendered, removing it from
+    /// [Self::find_faces_needing_thumbnails].
+    pub fn mark_thumbnail_generated(&mut self, face_id: FaceId) -> Result<()> {
+        let mut con = self.con.lock().unwrap();
+        let tx = con.transaction()?;
 
-                    nose_x,
-                    nose_y,
+        {
+            let mut stmt = tx.prepare_cached(
+                "UPDATE pictures_faces
+                SET thumbnail_generated = TRUE
+                WHERE face_id = ?1",
+            )?;
 
-                    right_mouth_corner_x,
-                    right_mouth_corner_y,
+            stmt.execute(params![face_id.id(),])?;
+        }
 
-                    left_mouth_corner_x,
-                    left_mouth_corner_y,
+        tx.commit()?;
+        Ok(())
+    }
 
-                    confidence,
+    /// Marks a face's thumbnail/bounds crops as permanently unrenderable (e.g. its source
+    /// picture has since been deleted), removing it from [Self::find_faces_needing_thumbnails]
+    /// without ever having written real crop files. Equivalent to [Self::mark_face_scan_broken]
+    /// for the thumbnail-render queue: a terminal state instead of an unbounded retry.
+    pub fn mark_thumbnail_broken(&mut self, face_id: FaceId) -> Result<()> {
+        let mut con = self.con.lock().unwrap();
+        let tx = con.transaction()?;
 
-                    is_face
-                ) VALUES (
-                    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
-                    ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, true
-                )
-                ",
+        {
+            let mut stmt = tx.prepare_cached(
+                "UPDATE pictures_faces
+                SET thumbnail_generated = TRUE, thumbnail_broken = TRUE
+                WHERE face_id = ?1",
             )?;
 
-            for face in faces {
-                // convert to relative path before saving to database
-                let thumbnail_path = face
-                    .thumbnail_path
-                    .strip_prefix(&self.cache_dir_base_path)?;
-                let bounds_path = face.bounds_path.strip_prefix(&self.cache_dir_base_path)?;
-
-                let right_eye = face.right_eye();
-                let left_eye = face.left_eye();
-                let nose = face.nose();
-                let right_mouth_corner = face.right_mouth_corner();
-                let left_mouth_corner = face.left_mouth_corner();
-
-                face_insert_stmt.execute(params![
-                    picture_id.id(),
-                    thumbnail_path.to_string_lossy(),
-                    bounds_path.to_string_lossy(),
-                    face.model_name,
-                    face.bounds.x,
-                    face.bounds.y,
-                    face.bounds.width,
-                    face.bounds.height,
-                    right_eye.map(|x| x.0),
-                    right_eye.map(|x| x.1),
-                    left_eye.map(|x| x.0),
-                    left_eye.map(|x| x.1),
-                    nose.map(|x| x.0),
-                    nose.map(|x| x.1),
-                    right_mouth_corner.map(|x| x.0),
-                    right_mouth_corner.map(|x| x.1),
-                    left_mouth_corner.map(|x| x.0),
-                    left_mouth_corner.map(|x| x.1),
-                    face.confidence
-                ])?;
-            }
+            stmt.execute(params![face_id.id(),])?;
         }
 
         tx.commit()?;
         Ok(())
     }
 
-    // FIXME probably need a mechanism to undo this in the likely event of user error.
+    /// Marks a face as not being a face at all. Reversible via [Self::undo].
     pub fn mark_not_a_face(&mut self, face_id: FaceId) -> Result<()> {
         let mut con = self.con.lock().unwrap();
         let tx = con.transaction()?;
 
         {
+            let prior_is_face: bool = tx.query_row(
+                "SELECT is_face FROM pictures_faces WHERE face_id = ?1",
+                params![face_id.id()],
+                |row| row.get(0),
+            )?;
+
             let mut stmt = tx.prepare_cached(
                 "UPDATE pictures_faces
                 SET
@@ -255,13 +642,242 @@ impl Repository {
             )?;
 
             stmt.execute(params![face_id.id(),])?;
+
+            Self::record_operation(
+                &tx,
+                &Operation::MarkedNotAFace {
+                    face_id,
+                    prior_is_face,
+                },
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Reverts the most recently applied reversible operation, moving the undo cursor back
+    /// one step. Returns `false` if there is nothing left to undo.
+    pub fn undo(&mut self) -> Result<bool> {
+        let mut con = self.con.lock().unwrap();
+        let tx = con.transaction()?;
+
+        let cursor = Self::read_cursor(&tx)?;
+        if cursor == 0 {
+            return Ok(false);
         }
 
+        let entry: Vec<u8> = tx.query_row(
+            "SELECT entry FROM operation_log WHERE operation_id = ?1",
+            params![cursor],
+            |row| row.get(0),
+        )?;
+        let op = Operation::decode(&entry)?;
+        Self::apply_inverse(&tx, &op)?;
+
+        tx.prepare_cached(
+            "UPDATE operation_log_cursor SET operation_id = (
+                SELECT COALESCE(MAX(operation_id), 0) FROM operation_log WHERE operation_id < ?1
+            ) WHERE id = 0",
+        )?
+        .execute(params![cursor])?;
+
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Re-applies the next undone operation, moving the undo cursor forward one step.
+    /// Returns `false` if there is nothing left to redo.
+    pub fn redo(&mut self) -> Result<bool> {
+        let mut con = self.con.lock().unwrap();
+        let tx = con.transaction()?;
+
+        let cursor = Self::read_cursor(&tx)?;
+
+        let next: Option<(i64, Vec<u8>)> = tx
+            .query_row(
+                "SELECT operation_id, entry FROM operation_log
+                 WHERE operation_id = (
+                    SELECT MIN(operation_id) FROM operation_log WHERE operation_id > ?1
+                 )",
+                params![cursor],
+                |row| std::result::Result::Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        let Some((operation_id, entry)) = next else {
+            return Ok(false);
+        };
+
+        let op = Operation::decode(&entry)?;
+        Self::apply_forward(&tx, &op)?;
+
+        tx.prepare_cached(
+            "INSERT INTO operation_log_cursor (id, operation_id) VALUES (0, ?1)
+             ON CONFLICT (id) DO UPDATE SET operation_id = ?1",
+        )?
+        .execute(params![operation_id])?;
+
         tx.commit()?;
+        Ok(true)
+    }
+
+    /// Lists the `limit` most recently applied operations, newest first, so the UI can show
+    /// and selectively revert recent people/face edits.
+    pub fn recent_operations(&self, limit: u32) -> Result<Vec<Operation>> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare(
+            "SELECT entry FROM operation_log
+             WHERE operation_id <= COALESCE(
+                (SELECT operation_id FROM operation_log_cursor WHERE id = 0), 0
+             )
+             ORDER BY operation_id DESC LIMIT ?1",
+        )?;
+
+        let result = stmt
+            .query_map(params![limit], |row| {
+                let entry: Vec<u8> = row.get(0)?;
+                std::result::Result::Ok(entry)
+            })?
+            .flatten()
+            .filter_map(|entry| Operation::decode(&entry).ok())
+            .collect();
+
+        Ok(result)
+    }
+
+    /// Appends `op` to the operation log and advances the undo cursor to it. Any operations
+    /// past the current cursor are an abandoned redo branch and are discarded first, same as
+    /// most undo stacks.
+    fn record_operation(tx: &rusqlite::Transaction, op: &Operation) -> Result<()> {
+        let cursor = Self::read_cursor(tx)?;
+        let encoded = op.encode()?;
+
+        tx.prepare_cached("DELETE FROM operation_log WHERE operation_id > ?1")?
+            .execute(params![cursor])?;
+
+        tx.prepare_cached(
+            "INSERT INTO operation_log (entry, created_ts) VALUES (?1, CURRENT_TIMESTAMP)",
+        )?
+        .execute(params![encoded])?;
+
+        let operation_id = tx.last_insert_rowid();
+
+        tx.prepare_cached(
+            "INSERT INTO operation_log_cursor (id, operation_id) VALUES (0, ?1)
+             ON CONFLICT (id) DO UPDATE SET operation_id = ?1",
+        )?
+        .execute(params![operation_id])?;
+
+        Ok(())
+    }
+
+    /// Reads the undo cursor: the `operation_id` of the most recently applied operation, or
+    /// `0` if nothing has been applied (or undone back past the start) yet.
+    fn read_cursor(tx: &rusqlite::Transaction) -> Result<i64> {
+        let cursor = tx
+            .query_row(
+                "SELECT operation_id FROM operation_log_cursor WHERE id = 0",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?
+            .unwrap_or(0);
+        Ok(cursor)
+    }
+
+    fn apply_inverse(tx: &rusqlite::Transaction, op: &Operation) -> Result<()> {
+        match op {
+            Operation::MarkedNotAFace {
+                face_id,
+                prior_is_face,
+            } => {
+                tx.prepare_cached("UPDATE pictures_faces SET is_face = ?2 WHERE face_id = ?1")?
+                    .execute(params![face_id.id(), prior_is_face])?;
+            }
+            Operation::AssignedPerson {
+                face_id,
+                prior_person_id,
+                prior_is_face,
+                ..
+            } => {
+                tx.prepare_cached(
+                    "UPDATE pictures_faces SET person_id = ?2, is_face = ?3 WHERE face_id = ?1",
+                )?
+                .execute(params![
+                    face_id.id(),
+                    prior_person_id.map(|p| p.id()),
+                    prior_is_face
+                ])?;
+            }
+            Operation::CreatedPerson { person_id, .. } => {
+                tx.prepare_cached("DELETE FROM people WHERE person_id = ?1")?
+                    .execute(params![person_id.id()])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_forward(tx: &rusqlite::Transaction, op: &Operation) -> Result<()> {
+        match op {
+            Operation::MarkedNotAFace { face_id, .. } => {
+                tx.prepare_cached("UPDATE pictures_faces SET is_face = FALSE WHERE face_id = ?1")?
+                    .execute(params![face_id.id()])?;
+            }
+            Operation::AssignedPerson {
+                face_id,
+                new_person_id,
+                ..
+            } => {
+                tx.prepare_cached(
+                    "UPDATE pictures_faces SET person_id = ?2, is_face = TRUE WHERE face_id = ?1",
+                )?
+                .execute(params![face_id.id(), new_person_id.id()])?;
+            }
+            Operation::CreatedPerson {
+                person_id,
+                name,
+                thumbnail_path,
+            } => {
+                tx.prepare_cached(
+                    "INSERT INTO people (person_id, name, thumbnail_path) VALUES (?1, ?2, ?3)",
+                )?
+                .execute(params![person_id.id(), name, thumbnail_path])?;
+            }
+        }
         Ok(())
     }
 
-    fn to_picture_id_path_tuple(&self, row: &Row<'_>) -> rusqlite::Result<(PictureId, PathBuf)> {
+    fn to_face_thumbnail_job(&self, row: &Row<'_>) -> rusqlite::Result<FaceThumbnailJob> {
+        let face_id = row.get("face_id").map(FaceId::new)?;
+
+        let picture_path: String = row.get("picture_path_b64")?;
+        let picture_path =
+            path_encoding::from_base64(&picture_path).map_err(|_| rusqlite::Error::InvalidQuery)?;
+        let picture_path = self.library_base_path.join(picture_path);
+
+        let thumbnail_path: String = row.get("thumbnail_path")?;
+        let thumbnail_path = self.cache_dir_base_path.join(thumbnail_path);
+
+        let bounds_path: String = row.get("bounds_path")?;
+        let bounds_path = self.cache_dir_base_path.join(bounds_path);
+
+        std::result::Result::Ok(FaceThumbnailJob {
+            face_id,
+            picture_path,
+            thumbnail_path,
+            bounds_path,
+            bounds_x: row.get("bounds_x")?,
+            bounds_y: row.get("bounds_y")?,
+            bounds_width: row.get("bounds_width")?,
+            bounds_height: row.get("bounds_height")?,
+        })
+    }
+
+    fn to_picture_id_path_ts_tuple(
+        &self,
+        row: &Row<'_>,
+    ) -> rusqlite::Result<(PictureId, PathBuf, String)> {
         let picture_id = row.get("picture_id").map(PictureId::new)?;
 
         let picture_path: String = row.get("picture_path_b64")?;
@@ -269,7 +885,23 @@ impl Repository {
             path_encoding::from_base64(&picture_path).map_err(|_| rusqlite::Error::InvalidQuery)?;
         let picture_path = self.library_base_path.join(picture_path);
 
-        std::result::Result::Ok((picture_id, picture_path))
+        let ordering_ts = row.get("ordering_ts")?;
+
+        std::result::Result::Ok((picture_id, picture_path, ordering_ts))
+    }
+
+    fn to_person(&self, row: &Row<'_>) -> rusqlite::Result<model::Person> {
+        let person_id = row.get("person_id").map(PersonId::new)?;
+        let name = row.get("name")?;
+        let thumbnail_path = row
+            .get("thumbnail_path")
+            .map(|p: String| self.cache_dir_base_path.join(p))?;
+
+        std::result::Result::Ok(model::Person {
+            person_id,
+            name,
+            thumbnail_path,
+        })
     }
 
     fn to_face_and_person(
@@ -310,4 +942,479 @@ impl Repository {
 
         std::result::Result::Ok((face, person))
     }
-}
\ No newline at end of file
+}
+
+/// Drives the face-scan job to completion, or until asked to stop, resuming from wherever a
+/// prior run left off via the `jobs` table.
+///
+/// This is the consumer that actually ties [Repository]'s scan queries to the [crate::jobs]
+/// subsystem: it loads a resumable job on startup, checkpoints its cursor after every picture
+/// in the same transaction as the scan results, and pauses cleanly when asked.
+pub struct FaceScanner {
+    people_repo: Repository,
+    jobs_repo: crate::jobs::Repository,
+    extractor: face_extractor::FaceExtractor,
+    stop_requested: Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl FaceScanner {
+    pub fn new(
+        people_repo: Repository,
+        jobs_repo: crate::jobs::Repository,
+        extractor: face_extractor::FaceExtractor,
+    ) -> FaceScanner {
+        FaceScanner {
+            people_repo,
+            jobs_repo,
+            extractor,
+            stop_requested: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+        }
+    }
+
+    /// A clonable handle the application can set from its shutdown path to ask a running
+    /// scan to pause cleanly instead of being killed mid-picture.
+    pub fn stop_handle(&self) -> Arc<std::sync::atomic::AtomicBool> {
+        self.stop_requested.clone()
+    }
+
+    /// Scans every picture that still needs one, resuming from the last checkpoint if a
+    /// prior run was paused or interrupted. Returns once the backlog is empty or
+    /// [Self::stop_handle] is set.
+    pub fn run(&mut self) -> Result<()> {
+        use crate::jobs::JobKind;
+        use std::sync::atomic::Ordering;
+
+        let resumed = self.jobs_repo.find_resumable_job(JobKind::FaceScan)?;
+
+        let (mut cursor, mut processed_count) = match &resumed {
+            Some(job) => (
+                crate::jobs::FaceScanCursor::decode(&job.state).ok(),
+                job.processed_count,
+            ),
+            None => (None, 0),
+        };
+
+        let pending = match &cursor {
+            Some(cursor) => self.people_repo.find_need_face_scan_after(cursor)?,
+            None => self.people_repo.find_need_face_scan()?,
+        };
+
+        let total_count = resumed
+            .as_ref()
+            .map(|job| job.total_count)
+            .unwrap_or(0)
+            .max(processed_count + pending.len() as i64);
+
+        for (picture_id, picture_path, ordering_ts) in pending {
+            if self.stop_requested.load(Ordering::SeqCst) {
+                self.jobs_repo.mark_paused(JobKind::FaceScan)?;
+                return Ok(());
+            }
+
+            let faces = match self.extractor.extract_faces(&picture_path) {
+                std::result::Result::Ok(faces) => faces,
+                Err(_) => {
+                    processed_count += 1;
+                    let next_cursor = crate::jobs::FaceScanCursor {
+                        last_ordering_ts: ordering_ts,
+                        last_picture_id: picture_id.id(),
+                    };
+                    self.people_repo.mark_face_scan_broken_checkpointed(
+                        &picture_id,
+                        &next_cursor,
+                        processed_count,
+                        total_count,
+                    )?;
+                    cursor = Some(next_cursor);
+                    continue;
+                }
+            };
+
+            let next_cursor = crate::jobs::FaceScanCursor {
+                last_ordering_ts: ordering_ts,
+                last_picture_id: picture_id.id(),
+            };
+            processed_count += 1;
+
+            self.people_repo.add_face_scans_checkpointed(
+                &picture_id,
+                &faces,
+                &next_cursor,
+                processed_count,
+                total_count,
+            )?;
+
+            cursor = Some(next_cursor);
+        }
+
+        let _ = cursor;
+        self.jobs_repo.mark_done(JobKind::FaceScan)?;
+        Ok(())
+    }
+}
+
+
+/// Renders the thumbnail/bounds crop files for faces that detection has recorded geometry
+/// for but hasn't rendered crops for yet. This is the worker [Repository::find_faces_needing_thumbnails]
+/// is queued for: it reads the source picture, crops using the stored bounds, writes the
+/// cache files, and flips `thumbnail_generated` so the face isn't picked up again.
+pub struct ThumbnailWorker {
+    people_repo: Repository,
+}
+
+/// Size, in pixels, of the square that a rendered face thumbnail is scaled to fit within for
+/// the cache, keeping the crop's aspect ratio.
+const THUMBNAIL_RENDER_SIZE: u32 = 256;
+
+impl ThumbnailWorker {
+    pub fn new(people_repo: Repository) -> ThumbnailWorker {
+        ThumbnailWorker { people_repo }
+    }
+
+    /// Renders every outstanding face crop. Continues past individual failures (e.g. a
+    /// source picture that has since been deleted): the face is marked broken instead of
+    /// being retried, so one permanently bad face doesn't get re-attempted on every run.
+    pub fn run(&mut self) -> Result<()> {
+        let jobs = self.people_repo.find_faces_needing_thumbnails()?;
+
+        for job in jobs {
+            match self.generate_one(&job) {
+                std::result::Result::Ok(()) => {
+                    self.people_repo.mark_thumbnail_generated(job.face_id)?
+                }
+                Err(e) => {
+                    warn!("Failed rendering thumbnail for face {}: {}", job.face_id, e);
+                    self.people_repo.mark_thumbnail_broken(job.face_id)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn generate_one(&self, job: &FaceThumbnailJob) -> Result<()> {
+        let source = image::open(&job.picture_path)?;
+
+        let crop = source.crop_imm(
+            job.bounds_x as u32,
+            job.bounds_y as u32,
+            job.bounds_width as u32,
+            job.bounds_height as u32,
+        );
+
+        if let Some(parent) = job.bounds_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        crop.save(&job.bounds_path)?;
+
+        let thumbnail = crop.resize(
+            THUMBNAIL_RENDER_SIZE,
+            THUMBNAIL_RENDER_SIZE,
+            image::imageops::FilterType::Lanczos3,
+        );
+        if let Some(parent) = job.thumbnail_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        thumbnail.save(&job.thumbnail_path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal schema covering only the columns this file's queries touch, so these tests can
+    /// run against a real in-memory connection instead of mocking `rusqlite` call-by-call.
+    fn test_con() -> Arc<Mutex<rusqlite::Connection>> {
+        let con = rusqlite::Connection::open_in_memory().unwrap();
+        con.execute_batch(
+            "CREATE TABLE pictures (
+                picture_id INTEGER PRIMARY KEY,
+                picture_path_b64 TEXT NOT NULL,
+                exif_created_ts TEXT,
+                exif_modified_ts TEXT,
+                fs_created_ts TEXT,
+                fs_modified_ts TEXT,
+                is_broken BOOLEAN
+            );
+            CREATE TABLE pictures_face_scans (
+                picture_id INTEGER PRIMARY KEY,
+                is_broken BOOLEAN,
+                face_count INTEGER,
+                scan_ts TEXT
+            );
+            CREATE TABLE pictures_faces (
+                face_id INTEGER PRIMARY KEY,
+                picture_id INTEGER,
+                person_id INTEGER,
+                thumbnail_path TEXT,
+                bounds_path TEXT,
+                model_name TEXT,
+                bounds_x REAL, bounds_y REAL, bounds_width REAL, bounds_height REAL,
+                right_eye_x REAL, right_eye_y REAL,
+                left_eye_x REAL, left_eye_y REAL,
+                nose_x REAL, nose_y REAL,
+                right_mouth_corner_x REAL, right_mouth_corner_y REAL,
+                left_mouth_corner_x REAL, left_mouth_corner_y REAL,
+                confidence REAL,
+                is_face BOOLEAN,
+                thumbnail_generated BOOLEAN,
+                thumbnail_broken BOOLEAN
+            );
+            CREATE TABLE people (
+                person_id INTEGER PRIMARY KEY,
+                name TEXT,
+                thumbnail_path TEXT
+            );
+            CREATE TABLE jobs (
+                kind TEXT PRIMARY KEY,
+                status TEXT,
+                state BLOB,
+                processed_count INTEGER,
+                total_count INTEGER,
+                updated_ts TEXT
+            );
+            CREATE TABLE operation_log (
+                operation_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                entry BLOB NOT NULL,
+                created_ts TEXT NOT NULL
+            );
+            CREATE TABLE operation_log_cursor (
+                id INTEGER PRIMARY KEY,
+                operation_id INTEGER NOT NULL
+            );",
+        )
+        .unwrap();
+        Arc::new(Mutex::new(con))
+    }
+
+    fn test_repo(con: Arc<Mutex<rusqlite::Connection>>) -> Repository {
+        let tmp = std::env::temp_dir();
+        Repository::open(&tmp, &tmp, con).unwrap()
+    }
+
+    fn insert_picture(con: &Arc<Mutex<rusqlite::Connection>>, picture_id: i64, ordering_ts: &str) {
+        con.lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO pictures (picture_id, picture_path_b64, exif_created_ts)
+                 VALUES (?1, 'cGhvdG8xMjMucG5n', ?2)",
+                params![picture_id, ordering_ts],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn find_need_face_scan_after_breaks_ties_on_picture_id() {
+        let con = test_con();
+        let same_ts = "2024-01-01T00:00:00Z";
+        insert_picture(&con, 10, same_ts);
+        insert_picture(&con, 20, same_ts);
+        insert_picture(&con, 30, same_ts);
+        let repo = test_repo(con);
+
+        let all = repo.find_need_face_scan().unwrap();
+        let ids: Vec<i64> = all.iter().map(|(id, _, _)| id.id()).collect();
+        assert_eq!(ids, vec![30, 20, 10]);
+
+        let cursor = crate::jobs::FaceScanCursor {
+            last_ordering_ts: same_ts.to_string(),
+            last_picture_id: 30,
+        };
+        let rest = repo.find_need_face_scan_after(&cursor).unwrap();
+        let rest_ids: Vec<i64> = rest.iter().map(|(id, _, _)| id.id()).collect();
+        assert_eq!(rest_ids, vec![20, 10]);
+    }
+
+    #[test]
+    fn mark_not_a_face_undo_redo_round_trips() {
+        let con = test_con();
+        con.lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO pictures_faces (face_id, is_face) VALUES (1, TRUE)",
+                [],
+            )
+            .unwrap();
+        let mut repo = test_repo(con.clone());
+        let face_id = FaceId::new(1);
+
+        repo.mark_not_a_face(face_id).unwrap();
+        let is_face: bool = con
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT is_face FROM pictures_faces WHERE face_id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(!is_face);
+
+        assert!(repo.undo().unwrap());
+        let is_face: bool = con
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT is_face FROM pictures_faces WHERE face_id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(is_face);
+        assert!(!repo.undo().unwrap());
+
+        assert!(repo.redo().unwrap());
+        let is_face: bool = con
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT is_face FROM pictures_faces WHERE face_id = 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(!is_face);
+        assert!(!repo.redo().unwrap());
+    }
+
+    #[test]
+    fn add_person_undo_removes_the_person_redo_restores_it() {
+        let con = test_con();
+        con.lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO pictures_faces (face_id, thumbnail_path, is_face)
+                 VALUES (1, 'faces/1.png', TRUE)",
+                [],
+            )
+            .unwrap();
+        let mut repo = test_repo(con);
+
+        let person_id = repo.add_person("Alice", FaceId::new(1)).unwrap();
+        assert_eq!(repo.all_people().unwrap().len(), 1);
+
+        assert!(repo.undo().unwrap());
+        assert_eq!(repo.all_people().unwrap().len(), 0);
+
+        assert!(repo.redo().unwrap());
+        let people = repo.all_people().unwrap();
+        assert_eq!(people.len(), 1);
+        assert_eq!(people[0].person_id.id(), person_id.id());
+        assert_eq!(people[0].name, "Alice");
+    }
+
+    #[test]
+    fn new_operation_truncates_the_redo_branch() {
+        let con = test_con();
+        con.lock()
+            .unwrap()
+            .execute(
+                "INSERT INTO pictures_faces (face_id, is_face) VALUES (1, TRUE)",
+                [],
+            )
+            .unwrap();
+        let mut repo = test_repo(con);
+        let face_id = FaceId::new(1);
+
+        repo.mark_not_a_face(face_id).unwrap();
+        repo.assign_face_to_person(face_id, PersonId::new(5))
+            .unwrap();
+        assert!(repo.undo().unwrap());
+
+        // Recording a new operation from here should discard the undone AssignedPerson entry
+        // instead of leaving it redoable alongside the new branch.
+        repo.mark_not_a_face(face_id).unwrap();
+        assert!(!repo.redo().unwrap());
+    }
+
+    fn thumbnail_worker_test_con_and_dir(dir_suffix: &str) -> (Arc<Mutex<rusqlite::Connection>>, PathBuf) {
+        let tmp = std::env::temp_dir().join(format!("fotema_repo_test_{}_{}", dir_suffix, std::process::id()));
+        std::fs::create_dir_all(&tmp).unwrap();
+
+        let con = rusqlite::Connection::open_in_memory().unwrap();
+        con.execute_batch(
+            "CREATE TABLE pictures (picture_id INTEGER PRIMARY KEY, picture_path_b64 TEXT);
+             CREATE TABLE pictures_faces (
+                face_id INTEGER PRIMARY KEY,
+                picture_id INTEGER,
+                thumbnail_path TEXT,
+                bounds_path TEXT,
+                bounds_x REAL, bounds_y REAL, bounds_width REAL, bounds_height REAL,
+                thumbnail_generated BOOLEAN,
+                thumbnail_broken BOOLEAN
+             );",
+        )
+        .unwrap();
+        con.execute(
+            "INSERT INTO pictures (picture_id, picture_path_b64) VALUES (1, 'cGhvdG8xMjMucG5n')",
+            [],
+        )
+        .unwrap();
+        con.execute(
+            "INSERT INTO pictures_faces (
+                face_id, picture_id, thumbnail_path, bounds_path,
+                bounds_x, bounds_y, bounds_width, bounds_height, thumbnail_generated
+            ) VALUES (1, 1, 'thumb.png', 'bounds.png', 2, 2, 10, 10, FALSE)",
+            [],
+        )
+        .unwrap();
+
+        (Arc::new(Mutex::new(con)), tmp)
+    }
+
+    #[test]
+    fn thumbnail_worker_renders_crop_and_marks_generated() {
+        let (con, tmp) = thumbnail_worker_test_con_and_dir("ok");
+        image::DynamicImage::ImageRgb8(image::RgbImage::new(20, 20))
+            .save(tmp.join("photo123.png"))
+            .unwrap();
+
+        let repo = Repository::open(&tmp, &tmp, con.clone()).unwrap();
+        let mut worker = ThumbnailWorker::new(repo);
+        worker.run().unwrap();
+
+        assert!(tmp.join("thumb.png").exists());
+        assert!(tmp.join("bounds.png").exists());
+        let (generated, broken): (bool, Option<bool>) = con
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT thumbnail_generated, thumbnail_broken FROM pictures_faces WHERE face_id = 1",
+                [],
+                |row| std::result::Result::Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(generated);
+        assert_ne!(broken, Some(true));
+
+        std::fs::remove_dir_all(&tmp).ok();
+    }
+
+    #[test]
+    fn thumbnail_worker_marks_broken_instead_of_retrying_forever() {
+        let (con, tmp) = thumbnail_worker_test_con_and_dir("broken");
+        // Deliberately do not write "photo123.png": the worker should treat a missing source
+        // picture as a permanent failure rather than leaving the face in the retry queue.
+
+        let repo = Repository::open(&tmp, &tmp, con.clone()).unwrap();
+        let mut worker = ThumbnailWorker::new(repo);
+        worker.run().unwrap();
+
+        let (generated, broken): (bool, Option<bool>) = con
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT thumbnail_generated, thumbnail_broken FROM pictures_faces WHERE face_id = 1",
+                [],
+                |row| std::result::Result::Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(generated, "must leave the retry queue even on failure");
+        assert_eq!(broken, Some(true));
+
+        std::fs::remove_dir_all(&tmp).ok();
+    }
+}
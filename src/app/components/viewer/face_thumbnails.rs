@@ -2,16 +2,22 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use relm4::gtk::{self, prelude::*};
-use relm4::gtk::gio;
+use relm4::gtk::{self, gdk, prelude::*};
 use relm4::*;
 use relm4::prelude::*;
 use crate::fl;
 use fotema_core::people;
+use fotema_core::people::model::Person;
+use fotema_core::people::{FaceId, PersonId};
 use fotema_core::PictureId;
 
-use tracing::{debug, info};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 
+use tracing::{debug, error, info};
+
+/// Fixed size, in pixels, that every face crop is decoded and scaled down to before display.
+const FACE_THUMBNAIL_SIZE: u32 = 50;
 
 #[derive(Debug)]
 pub enum FaceThumbnailsInput {
@@ -20,6 +26,31 @@ pub enum FaceThumbnailsInput {
 
     // The photo/video page has been hidden so any playing media should stop.
     Hide,
+
+    // A face crop has finished decoding on a background task.
+    ThumbnailDecoded(FaceId, DecodedThumbnail),
+
+    // A face crop failed to decode (e.g. the file is missing or corrupt).
+    ThumbnailFailed(FaceId),
+
+    // The user picked an existing person for a face from the assignment popover.
+    AssignPerson(FaceId, PersonId),
+
+    // The user typed a new person's name and confirmed "Create new person…" for a face.
+    CreatePerson(FaceId, String),
+
+    // The user marked a thumbnail as not being a face at all.
+    NotAFace(FaceId),
+}
+
+/// Raw RGBA pixels for a decoded face crop, sized to [FACE_THUMBNAIL_SIZE]. Kept as plain
+/// bytes so it can cross from the background decode task into a `gdk::Texture`, which isn't
+/// `Send`, on the component's own thread.
+#[derive(Debug)]
+pub struct DecodedThumbnail {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
 }
 
 #[derive(Debug)]
@@ -31,6 +62,25 @@ pub struct FaceThumbnails {
     people_repo: people::Repository,
 
     face_thumbnails: gtk::Box,
+
+    /// Picture currently being shown, so the strip can be rebuilt after a person
+    /// assignment without the caller having to re-send [FaceThumbnailsInput::View].
+    current_picture: Option<PictureId>,
+
+    /// Decoded and scaled face crops, ready to display. Evicted down to the current
+    /// picture's faces on every [Self::render] so this doesn't grow for the life of the
+    /// session while browsing a large library.
+    ready: HashMap<FaceId, gdk::Texture>,
+
+    /// Faces currently being decoded on a background task. Not evicted on [Self::render] like
+    /// [Self::ready]/[Self::failed]: an entry here clears itself out once the decode completes
+    /// (see `ThumbnailDecoded`/`ThumbnailFailed`), and evicting it early would just cause a
+    /// redundant second decode if the user navigates back to the picture before that happens.
+    pending: HashSet<FaceId>,
+
+    /// Faces whose crop failed to decode. Rendered as a placeholder instead of being
+    /// silently dropped. Evicted the same way as [Self::ready].
+    failed: HashSet<FaceId>,
 }
 
 #[relm4::component(pub async)]
@@ -55,69 +105,299 @@ impl SimpleAsyncComponent for FaceThumbnails {
 
         let widgets = view_output!();
 
-/*
-        let face_thumbnails = gtk::Box::builder()
-            .orientation(gtk::Orientation::Horizontal)
-            .spacing(8)
-            .build();
-*/
         let model = Self {
             people_repo,
             face_thumbnails: widgets.face_thumbnails.clone(),
+            current_picture: None,
+            ready: HashMap::new(),
+            pending: HashSet::new(),
+            failed: HashSet::new(),
         };
 
         AsyncComponentParts { model, widgets }
     }
 
-    async fn update(&mut self, msg: Self::Input, _sender: AsyncComponentSender<Self>) {
+    async fn update(&mut self, msg: Self::Input, sender: AsyncComponentSender<Self>) {
         match msg {
             FaceThumbnailsInput::Hide => {
                 self.face_thumbnails.remove_all();
+                self.current_picture = None;
             },
             FaceThumbnailsInput::View(picture_id) => {
                 info!("Showing faces for {}", picture_id);
+                self.current_picture = Some(picture_id.clone());
+                self.render(picture_id, &sender);
+            },
+            FaceThumbnailsInput::ThumbnailDecoded(face_id, thumbnail) => {
+                self.pending.remove(&face_id);
 
-                self.face_thumbnails.remove_all();
+                let bytes = glib::Bytes::from_owned(thumbnail.rgba);
+                let texture = gdk::MemoryTexture::new(
+                    thumbnail.width as i32,
+                    thumbnail.height as i32,
+                    gdk::MemoryFormat::R8g8b8a8,
+                    &bytes,
+                    thumbnail.width as usize * 4,
+                )
+                .upcast::<gdk::Texture>();
 
-                if let Ok(faces) = self.people_repo.find_faces(&picture_id) {
-                    debug!("Found {} faces", faces.len());
-                    faces.into_iter()
-                        .filter(|face| face.thumbnail_path.exists())
-                        .for_each(|face| {
-                            let menu_model = gio::Menu::new();
-                            let menu_item = gio::MenuItem::new(Some("test"), None);
+                self.ready.insert(face_id, texture);
+                self.refresh(&sender);
+            },
+            FaceThumbnailsInput::ThumbnailFailed(face_id) => {
+                self.pending.remove(&face_id);
+                self.failed.insert(face_id);
+                self.refresh(&sender);
+            },
+            FaceThumbnailsInput::AssignPerson(face_id, person_id) => {
+                if let Err(e) = self.people_repo.assign_face_to_person(face_id, person_id) {
+                    error!("Failed assigning face to person: {}", e);
+                }
+                self.refresh(&sender);
+            },
+            FaceThumbnailsInput::CreatePerson(face_id, name) => {
+                let assigned = self
+                    .people_repo
+                    .add_person(&name, face_id)
+                    .and_then(|person_id| self.people_repo.assign_face_to_person(face_id, person_id));
+                if let Err(e) = assigned {
+                    error!("Failed creating person: {}", e);
+                }
+                self.refresh(&sender);
+            },
+            FaceThumbnailsInput::NotAFace(face_id) => {
+                if let Err(e) = self.people_repo.mark_not_a_face(face_id) {
+                    error!("Failed marking thumbnail as not a face: {}", e);
+                }
+                self.refresh(&sender);
+            },
+        }
+    }
+}
 
-                            let pop = gtk::PopoverMenu::builder()
-                                .menu_model(&menu_model)
-                                .build();
+impl FaceThumbnails {
+    /// Re-renders the currently viewed picture's face strip, e.g. after a person
+    /// assignment changes what should be shown.
+    fn refresh(&mut self, sender: &AsyncComponentSender<Self>) {
+        if let Some(picture_id) = self.current_picture.clone() {
+            self.render(picture_id, sender);
+        }
+    }
 
-                            let thumbnail = gtk::Picture::for_filename(&face.thumbnail_path);
-                            thumbnail.set_content_fit(gtk::ContentFit::ScaleDown);
-                            thumbnail.set_width_request(50);
-                            thumbnail.set_height_request(50);
+    fn render(&mut self, picture_id: PictureId, sender: &AsyncComponentSender<Self>) {
+        self.face_thumbnails.remove_all();
 
-                            let children = gtk::Box::new(gtk::Orientation::Vertical, 0);
-                            children.append(&thumbnail);
-                            children.append(&pop);
+        let Ok(faces) = self.people_repo.find_faces(&picture_id) else {
+            return;
+        };
+        debug!("Found {} faces", faces.len());
 
-                            let frame = gtk::Frame::new(None);
-                            frame.set_child(Some(&children));
-                            frame.add_css_class("face-small");
+        // Evict anything left over from a previously viewed picture so these caches stay
+        // bounded by one picture's worth of faces instead of growing for as long as the
+        // session lasts. `pending` is left alone: a face can still be mid-decode for a
+        // picture the user has briefly navigated away from and back to, and evicting it
+        // here would just cause Self::decode_thumbnail to be dispatched for it a second time.
+        let face_ids: HashSet<FaceId> = faces.iter().map(|(face, _)| face.face_id).collect();
+        self.ready.retain(|face_id, _| face_ids.contains(face_id));
+        self.failed.retain(|face_id| face_ids.contains(face_id));
 
-                            let click = gtk::GestureClick::new();
-                            click.connect_released(move |_click,_,_,_| {
-                                pop.popup();
-                            });
+        let people = self.people_repo.all_people().unwrap_or_default();
 
-                            // if we get a stop message, then we are not dealing with a single-click.
-                            click.connect_stopped(move |click| click.reset());
+        for (face, person) in faces {
+            let frame = gtk::Frame::new(None);
+            frame.add_css_class("face-small");
 
-                            frame.add_controller(click);
+            let children = gtk::Box::new(gtk::Orientation::Vertical, 0);
+            frame.set_child(Some(&children));
 
-                            self.face_thumbnails.append(&frame);
-                        });
+            if let Some(texture) = self.ready.get(&face.face_id) {
+                children.append(&Self::thumbnail_picture(texture));
+            } else if self.failed.contains(&face.face_id) {
+                children.append(&Self::placeholder_picture());
+            } else {
+                children.append(&Self::placeholder_picture());
+                if self.pending.insert(face.face_id) {
+                    Self::decode_thumbnail(face.face_id, face.thumbnail_path, sender);
                 }
-            },
+            }
+
+            if let Some(person) = person {
+                let label = gtk::Label::new(Some(&person.name));
+                label.add_css_class("caption");
+                children.append(&label);
+            }
+
+            let pop = Self::assignment_popover(face.face_id, &people, sender);
+            children.append(&pop);
+
+            let click = gtk::GestureClick::new();
+            click.connect_released(move |_click, _, _, _| {
+                pop.popup();
+            });
+
+            // if we get a stop message, then we are not dealing with a single-click.
+            click.connect_stopped(move |click| click.reset());
+
+            frame.add_controller(click);
+
+            self.face_thumbnails.append(&frame);
         }
     }
+
+    /// Builds the "who is this?" popover for a face: a searchable list of known people,
+    /// plus actions to create a new person or mark the crop as not a face at all.
+    fn assignment_popover(
+        face_id: FaceId,
+        people: &[Person],
+        sender: &AsyncComponentSender<Self>,
+    ) -> gtk::Popover {
+        let search = gtk::SearchEntry::new();
+        search.set_placeholder_text(Some(&fl!("people-search-placeholder")));
+
+        let list = gtk::ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::None);
+
+        for person in people {
+            let row = gtk::ListBoxRow::new();
+            row.set_widget_name(&person.person_id.id().to_string());
+
+            let label = gtk::Label::new(Some(&person.name));
+            label.set_halign(gtk::Align::Start);
+            row.set_child(Some(&label));
+
+            list.append(&row);
+        }
+
+        let not_a_face_row = gtk::ListBoxRow::new();
+        not_a_face_row.set_widget_name("not-a-face");
+        not_a_face_row.set_child(Some(&gtk::Label::new(Some(&fl!("people-not-a-face")))));
+        list.append(&not_a_face_row);
+
+        {
+            let search = search.clone();
+            list.set_filter_func(move |row| {
+                let query = search.text().to_lowercase();
+                if query.is_empty() {
+                    return true;
+                }
+                row.widget_name() == "not-a-face"
+                    || row
+                        .child()
+                        .and_downcast::<gtk::Label>()
+                        .is_some_and(|label| label.text().to_lowercase().contains(&query))
+            });
+        }
+
+        {
+            let list = list.clone();
+            search.connect_search_changed(move |_| list.invalidate_filter());
+        }
+
+        let popover = gtk::Popover::new();
+
+        {
+            let sender = sender.clone();
+            let popover = popover.clone();
+            list.connect_row_activated(move |_list, row| match row.widget_name().as_str() {
+                "not-a-face" => {
+                    sender.input(FaceThumbnailsInput::NotAFace(face_id));
+                    popover.popdown();
+                }
+                name => {
+                    if let Ok(person_id) = name.parse::<i64>() {
+                        sender.input(FaceThumbnailsInput::AssignPerson(
+                            face_id,
+                            PersonId::new(person_id),
+                        ));
+                        popover.popdown();
+                    }
+                }
+            });
+        }
+
+        let create_entry = gtk::Entry::new();
+        create_entry.set_placeholder_text(Some(&fl!("people-create-new-person")));
+        {
+            let sender = sender.clone();
+            let popover = popover.clone();
+            create_entry.connect_activate(move |entry| {
+                let name = entry.text().to_string();
+                if !name.is_empty() {
+                    sender.input(FaceThumbnailsInput::CreatePerson(face_id, name));
+                    entry.set_text("");
+                    popover.popdown();
+                }
+            });
+        }
+
+        let content = gtk::Box::new(gtk::Orientation::Vertical, 4);
+        content.append(&search);
+        content.append(&list);
+        content.append(&create_entry);
+        popover.set_child(Some(&content));
+
+        popover
+    }
+
+    fn thumbnail_picture(texture: &gdk::Texture) -> gtk::Picture {
+        let picture = gtk::Picture::for_paintable(texture);
+        picture.set_content_fit(gtk::ContentFit::ScaleDown);
+        picture.set_width_request(FACE_THUMBNAIL_SIZE as i32);
+        picture.set_height_request(FACE_THUMBNAIL_SIZE as i32);
+        picture
+    }
+
+    /// A neutral frame shown while a face crop is loading, or in place of one that failed
+    /// to decode, so a face never just vanishes from the strip.
+    fn placeholder_picture() -> gtk::Box {
+        let placeholder = gtk::Box::new(gtk::Orientation::Vertical, 0);
+        placeholder.set_width_request(FACE_THUMBNAIL_SIZE as i32);
+        placeholder.set_height_request(FACE_THUMBNAIL_SIZE as i32);
+        placeholder.add_css_class("face-thumbnail-placeholder");
+        placeholder
+    }
+
+    /// Decodes and scales a face crop on a background task, then reports the result back to
+    /// the component so the UI thread never blocks on file I/O or image decoding.
+    fn decode_thumbnail(
+        face_id: FaceId,
+        path: PathBuf,
+        sender: &AsyncComponentSender<Self>,
+    ) {
+        let input_sender = sender.input_sender().clone();
+
+        relm4::spawn(async move {
+            let result = tokio::task::spawn_blocking(move || {
+                let img = image::open(&path)?
+                    .resize_exact(
+                        FACE_THUMBNAIL_SIZE,
+                        FACE_THUMBNAIL_SIZE,
+                        image::imageops::FilterType::Lanczos3,
+                    )
+                    .to_rgba8();
+
+                let (width, height) = img.dimensions();
+                anyhow::Ok(DecodedThumbnail {
+                    width,
+                    height,
+                    rgba: img.into_raw(),
+                })
+            })
+            .await;
+
+            match result {
+                Ok(Ok(thumbnail)) => {
+                    let _ = input_sender.send(FaceThumbnailsInput::ThumbnailDecoded(face_id, thumbnail));
+                }
+                Ok(Err(e)) => {
+                    error!("Failed decoding face thumbnail: {}", e);
+                    let _ = input_sender.send(FaceThumbnailsInput::ThumbnailFailed(face_id));
+                }
+                Err(e) => {
+                    error!("Face thumbnail decode task panicked: {}", e);
+                    let _ = input_sender.send(FaceThumbnailsInput::ThumbnailFailed(face_id));
+                }
+            }
+        });
+    }
 }